@@ -1,6 +1,21 @@
-use crate::commands::{AccessPointConnectCommand, WifiModeCommand};
-use crate::urc::URCMessages;
+use crate::commands::{
+    AccessPointConnectCommand, CipCloseCommand, CipDinfoCommand, CipDomainCommand, CipMuxCommand,
+    CipSendCommand, CipSendToCommand, CipServerCommand, CipSslConfCommand, CipSslSniCommand,
+    CipStartCommand, CipStartUdpCommand, CipStateQueryCommand, CwStateQueryCommand,
+    CwdhcpsCommand, CwsapCommand, SendDataCommand, WifiModeCommand, MAX_LINKS, MAX_SEND_LEN,
+};
+use crate::urc::{URCMessages, MAX_IPD_CHUNK};
 use atat::{AtatClient, Error as AtError};
+use embedded_nal::{
+    AddrType, Dns, IpAddr, Ipv4Addr, SocketAddr, TcpClientStack, TcpFullStack, UdpClientStack,
+};
+use heapless::Vec;
+
+/// Number of bytes buffered per link between `receive()` calls
+const RX_BUFFER_SIZE: usize = 512;
+
+/// Number of distinct UDP datagrams buffered per link between `receive()` calls
+const MAX_DATAGRAMS: usize = 4;
 
 /// Central client for network communication
 pub struct Adapter<A: AtatClient> {
@@ -12,6 +27,210 @@ pub struct Adapter<A: AtatClient> {
 
     /// True if an IP was assigned by access point. Get updated by URC message.
     ip_assigned: bool,
+
+    /// `true` once `join()` has associated at least once, even if the association was
+    /// later lost. Lets `get_join_state()` tell "never attempted" apart from
+    /// "disconnected after being joined".
+    ever_joined: bool,
+
+    /// Per-link socket state, indexed by link id. Populated once `AT+CIPMUX=1`
+    /// has been sent and kept up to date by `handle_single_urc()`.
+    links: [Link; MAX_LINKS],
+
+    /// `true` once `AT+CIPMUX=1` has been sent successfully
+    multiplexing_enabled: bool,
+
+    /// Number of stations currently associated to our SoftAP. Gets updated by URC messages.
+    connected_stations: u8,
+
+    /// Port the TCP server is listening on, once `bind()` has been called
+    server_port: Option<u16>,
+
+    /// Link ids that connected while the server was listening, waiting to be picked up by `accept()`
+    pending_accepts: Vec<usize, MAX_LINKS>,
+
+    /// Mints the next [`Link::token`]. Only ever incremented, never reset, so every
+    /// claim of a link id - whether via `socket()` or a connection accepted by the
+    /// server - gets a value distinct from whatever came before it on that same id.
+    next_link_token: u32,
+}
+
+/// Which `+IPD` framing a link uses. `AT+CIPDINFO` is a single global toggle that
+/// cannot tell `handle_single_urc()` which individual links are UDP, so each link
+/// tracks its own protocol instead of inferring it from whether a given `+IPD`
+/// happened to carry address fields.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum LinkProtocol {
+    /// TCP or TLS: a single byte stream, buffered in `rx_buffer`
+    Stream,
+
+    /// UDP: discrete datagrams, buffered in `rx_datagrams`
+    Datagram,
+}
+
+/// Per-link bookkeeping for a multiplexed TCP/UDP connection
+#[derive(Default)]
+struct Link {
+    /// `true` once this link id has been handed out by `socket()`
+    in_use: bool,
+
+    /// `true` once the `<id>,CONNECT` URC has been seen
+    connected: bool,
+
+    /// `true` once the `<id>,CLOSED` URC has been seen
+    closed: bool,
+
+    /// Which `+IPD` framing applies to this link. `None` until a `connect()`/
+    /// `connect_tls()` call actually completes or the link is accepted by the
+    /// server, since a freshly claimed but not yet connected link has no protocol yet.
+    protocol: Option<LinkProtocol>,
+
+    /// Identifies the current occupant of this link id. Set to a freshly minted value
+    /// every time the id is handed to a new `Socket`/`UdpSocket` (`claim_link()`, or
+    /// `handle_single_urc()` for a connection accepted by the server) and reset to `0`
+    /// when the id is freed by `bind()`/`close()`, so a stale handle left over from a
+    /// freed link - e.g. the listener `Socket` `bind()` frees immediately - can be told
+    /// apart from whatever later reused that id. `0` means "never claimed or freed".
+    token: u32,
+
+    /// Stream data received through plain TCP `+IPD` URCs, waiting to be drained by
+    /// `TcpClientStack::receive()`. Concatenating is correct here since TCP has no
+    /// datagram boundaries.
+    rx_buffer: Vec<u8, RX_BUFFER_SIZE>,
+
+    /// UDP datagrams received through address-carrying `+IPD` URCs, each kept as a
+    /// discrete `(sender, bytes)` unit so `UdpClientStack::receive()` never merges two
+    /// senders' payloads or reports the wrong source address. Oldest-first; silently
+    /// drops the newest datagram if the queue is already full.
+    rx_datagrams: Vec<Datagram, MAX_DATAGRAMS>,
+}
+
+/// A single buffered UDP datagram, tagged with the address it arrived from
+struct Datagram {
+    remote: SocketAddr,
+    data: Vec<u8, MAX_IPD_CHUNK>,
+}
+
+/// Handle to a single multiplexed TCP/UDP connection, as returned by
+/// [`TcpClientStack::socket`]
+#[derive(Debug, PartialEq, Eq)]
+pub struct Socket {
+    link_id: usize,
+
+    /// Captured from `Link::token` when this handle was issued; see its docs
+    token: u32,
+}
+
+/// Handle to a single multiplexed UDP "connection", as returned by
+/// [`UdpClientStack::socket`]
+#[derive(Debug, PartialEq, Eq)]
+pub struct UdpSocket {
+    link_id: usize,
+
+    /// Captured from `Link::token` when this handle was issued; see its docs
+    token: u32,
+
+    /// Default destination, set once `connect()` has opened the link
+    remote: Option<SocketAddr>,
+}
+
+/// Possible errors when using a [`Socket`]
+#[derive(Clone, Debug, PartialEq)]
+pub enum SocketError {
+    /// All `MAX_LINKS` link ids are currently in use
+    NoAvailableLink,
+
+    /// Error while switching into multi-connection mode
+    ModeError(AtError),
+
+    /// Error while opening the connection
+    ConnectError(AtError),
+
+    /// Error while sending a payload
+    SendError(AtError),
+
+    /// Error while closing the connection
+    CloseError(AtError),
+
+    /// Error while configuring or performing the TLS handshake
+    TlsHandshakeError(AtError),
+
+    /// The peer closed the connection. Also returned by `UdpClientStack::send` on a
+    /// socket that has not been connected yet.
+    ConnectionClosed,
+
+    /// A single UDP datagram is larger than `MAX_SEND_LEN`. Unlike `TcpClientStack::send`,
+    /// `UdpClientStack::send` has no byte count to report a short write with, so sending
+    /// only part of the datagram would silently deliver a truncated, corrupted payload.
+    DatagramTooLarge,
+
+    /// This socket's link id was already reclaimed - e.g. `bind()` frees its listener
+    /// socket's link id immediately, or the link was already closed - and may since
+    /// have been handed out to an unrelated connection. Rejected rather than acting
+    /// on whatever now occupies the slot.
+    UnknownSocket,
+
+    /// Received an unexpected WouldBlock. The most common cause of errors is an incorrect mode of the client.
+    /// This must be either timeout or blocking.
+    UnexpectedWouldBlock,
+}
+
+/// Certificate verification performed during the TLS handshake
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TlsVerifyMode {
+    /// No certificate is verified; vulnerable to MITM, only useful for local testing
+    None,
+
+    /// The server certificate is verified against `ca_cert_index`
+    ServerOnly,
+
+    /// Both the server certificate and our own client certificate are verified
+    Mutual,
+}
+
+impl TlsVerifyMode {
+    /// Maps to the `AT+CIPSSLCCONF` `auth_mode` parameter
+    fn auth_mode(self) -> u8 {
+        match self {
+            Self::None => 0,
+            Self::ServerOnly => 2,
+            Self::Mutual => 3,
+        }
+    }
+}
+
+/// Configuration for [`Adapter::connect_tls`]
+#[derive(Clone, Debug)]
+pub struct TlsConfig<'a> {
+    /// SNI hostname presented during the handshake. Defaults to the connection's `host` if `None`.
+    pub sni: Option<&'a str>,
+
+    /// Certificate verification mode
+    pub verify_mode: TlsVerifyMode,
+
+    /// Flash slot of the client certificate/key, used by [`TlsVerifyMode::Mutual`].
+    /// `None` selects slot `0`.
+    pub client_cert_index: Option<u8>,
+
+    /// Flash slot of the CA certificate, used by [`TlsVerifyMode::ServerOnly`] and
+    /// [`TlsVerifyMode::Mutual`]. `None` selects slot `0`.
+    pub ca_cert_index: Option<u8>,
+}
+
+/// Possible errors when resolving a hostname
+#[derive(Clone, Debug, PartialEq)]
+pub enum DnsError {
+    /// Error while sending `AT+CIPDOMAIN` or parsing its response
+    ResolveError(AtError),
+
+    /// ESP-AT has no reverse lookup, so `get_host_by_address` can never succeed; also
+    /// returned by `get_host_by_name(_, AddrType::Either)`, since `AT+CIPDOMAIN` can only
+    /// resolve a single address family per query
+    Unsupported,
+
+    /// Received an unexpected WouldBlock. The most common cause of errors is an incorrect mode of the client.
+    /// This must be either timeout or blocking.
+    UnexpectedWouldBlock,
 }
 
 /// Possible errors when joining an access point
@@ -26,6 +245,9 @@ pub enum JoinError {
     /// Error while setting WIFI credentials
     ConnectError(AtError),
 
+    /// Error while querying the connection status
+    QueryError(AtError),
+
     /// Given SSD is longer then the max. size of 32 chars
     InvalidSSDLength,
 
@@ -37,14 +259,90 @@ pub enum JoinError {
     UnexpectedWouldBlock,
 }
 
+/// Typed WIFI association state, as reported by `AT+CWSTATE?`
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ConnectionStatus {
+    /// No WIFI connection has been attempted yet
+    NotStarted,
+
+    /// Associated with an access point, or in the process of (re-)connecting
+    Connecting,
+
+    /// Associated with an access point, but no IP has been assigned yet
+    Connected,
+
+    /// Associated with an access point and an IP was assigned
+    GotIp,
+
+    /// Not associated with an access point, e.g. after the connection was lost
+    Disconnected,
+
+    /// An unrecognized/error status code was reported; treat as not connected
+    ConnectFailed,
+}
+
+/// Maps the `<state>` field of `AT+CWSTATE?`'s response to a [`ConnectionStatus`]
+fn cwstate_to_connection_status(state: u8) -> ConnectionStatus {
+    match state {
+        0 => ConnectionStatus::NotStarted,
+        1 => ConnectionStatus::Connected,
+        2 => ConnectionStatus::GotIp,
+        3 => ConnectionStatus::Connecting,
+        4 => ConnectionStatus::Disconnected,
+        _ => ConnectionStatus::ConnectFailed,
+    }
+}
+
 /// Current WIFI connection state
 #[derive(Copy, Clone, Debug)]
 pub struct JoinState {
-    /// True if connected to an WIFI access point
-    pub connected: bool,
+    /// Typed connection status, see [`ConnectionStatus`]
+    pub status: ConnectionStatus,
+}
 
-    /// True if an IP was assigned
-    pub ip_assigned: bool,
+/// Configuration for [`Adapter::start_ap`]
+#[derive(Clone, Debug)]
+pub struct ApConfig<'a> {
+    /// SSID announced by the SoftAP, max. 32 chars
+    pub ssid: &'a str,
+
+    /// Password for the SoftAP, max. 63 chars. Empty opens the network.
+    pub password: &'a str,
+
+    /// WIFI channel the SoftAP broadcasts on
+    pub channel: u8,
+
+    /// Maximum number of stations that may join at once
+    pub max_connections: u8,
+
+    /// DHCP lease range and lease time (in minutes) handed out to joining stations.
+    /// ESP-AT has no DHCP option for advertising a DNS server, so pointing clients at
+    /// a captive portal has to be done at the application layer (e.g. a DNS/HTTP
+    /// server answering on the SoftAP's own address), not through this config.
+    pub dhcp_range: Option<(Ipv4Addr, Ipv4Addr, u16)>,
+}
+
+/// Possible errors when starting or stopping the SoftAP
+#[derive(Clone, Debug, PartialEq)]
+pub enum ApError {
+    /// Error wile setting WIFI mode to SoftAP
+    ModeError(AtError),
+
+    /// Error while configuring the SoftAP SSID/password/channel
+    ConfigureError(AtError),
+
+    /// Error while configuring the DHCP lease range/time
+    DhcpError(AtError),
+
+    /// Given SSID is longer then the max. size of 32 chars
+    InvalidSSIDLength,
+
+    /// Given password is longer then the max. size of 63 chars
+    InvalidPasswordLength,
+
+    /// Received an unexpected WouldBlock. The most common cause of errors is an incorrect mode of the client.
+    /// This must be either timeout or blocking.
+    UnexpectedWouldBlock,
 }
 
 impl<A: AtatClient> Adapter<A> {
@@ -54,24 +352,63 @@ impl<A: AtatClient> Adapter<A> {
             client,
             joined: false,
             ip_assigned: false,
+            ever_joined: false,
+            links: Default::default(),
+            multiplexing_enabled: false,
+            connected_stations: 0,
+            server_port: None,
+            pending_accepts: Vec::new(),
+            // `0` is reserved to mean "never claimed" (see `Link::token`), so the first
+            // minted token is `1`.
+            next_link_token: 1,
         }
     }
 
+    /// Mints a token distinct from every token previously returned, used to mark a
+    /// link id as freshly claimed; see `Link::token`
+    fn next_token(&mut self) -> u32 {
+        let token = self.next_link_token;
+        self.next_link_token = self.next_link_token.wrapping_add(1);
+        token
+    }
+
     /// Connects to an WIFI access point and returns the connection state
     ///
     /// Note:
     /// If the connection was not successful or is lost, the ESP-AT will try independently fro time
     /// to time (by default every second) to establish connection to the network. The status can be
-    /// queried using `get_join_state()`.
+    /// queried passively using `get_join_state()`, or actively using `query_connection_status()`.
     pub fn join(&mut self, ssid: &str, key: &str) -> Result<JoinState, JoinError> {
         self.set_station_mode()?;
         self.connect_access_point(ssid, key)?;
         self.process_urc_messages();
 
-        Ok(JoinState {
-            connected: self.joined,
-            ip_assigned: self.ip_assigned,
-        })
+        Ok(self.get_join_state())
+    }
+
+    /// Returns the connection state as tracked passively through URC messages.
+    /// Use `query_connection_status()` to actively ask the module instead.
+    pub fn get_join_state(&self) -> JoinState {
+        let status = match (self.joined, self.ip_assigned, self.ever_joined) {
+            (true, true, _) => ConnectionStatus::GotIp,
+            (true, false, _) => ConnectionStatus::Connected,
+            (false, _, true) => ConnectionStatus::Disconnected,
+            (false, _, false) => ConnectionStatus::NotStarted,
+        };
+
+        JoinState { status }
+    }
+
+    /// Actively queries the module for its WIFI association state via `AT+CWSTATE?`,
+    /// instead of relying on passively observed URC messages
+    pub fn query_connection_status(&mut self) -> Result<ConnectionStatus, JoinError> {
+        let command = CwStateQueryCommand::new();
+        let response = self.client.send(&command).map_err(|error| match error {
+            nb::Error::Other(other) => JoinError::QueryError(other),
+            nb::Error::WouldBlock => JoinError::UnexpectedWouldBlock,
+        })?;
+
+        Ok(cwstate_to_connection_status(response.state))
     }
 
     /// Processes all pending messages in the queue
@@ -87,8 +424,82 @@ impl<A: AtatClient> Adapter<A> {
                 self.ip_assigned = false;
             }
             Some(URCMessages::ReceivedIP) => self.ip_assigned = true,
-            Some(URCMessages::WifiConnected) => self.joined = true,
+            Some(URCMessages::WifiConnected) => {
+                self.joined = true;
+                self.ever_joined = true;
+            }
             Some(URCMessages::Ready) => {}
+            Some(URCMessages::LinkConnected(link_id)) => {
+                let server_listening = self.server_port.is_some();
+                let accepted_by_server =
+                    matches!(self.links.get(link_id), Some(link) if server_listening && !link.in_use);
+
+                // Minted before the mutable borrow below so a link accepted by the server
+                // gets a token distinct from whatever last occupied this id.
+                let fresh_token = accepted_by_server.then(|| self.next_token());
+
+                if let Some(link) = self.links.get_mut(link_id) {
+                    link.connected = true;
+                    if let Some(token) = fresh_token {
+                        link.in_use = true;
+                        link.token = token;
+                        // `AT+CIPSERVER` only ever accepts plain TCP connections.
+                        link.protocol = Some(LinkProtocol::Stream);
+                    }
+                }
+
+                if accepted_by_server {
+                    // Best effort: dropped if the queue is already full of unclaimed links
+                    let _ = self.pending_accepts.push(link_id);
+                }
+            }
+            Some(URCMessages::LinkClosed(link_id)) => {
+                if let Some(link) = self.links.get_mut(link_id) {
+                    link.connected = false;
+                    link.closed = true;
+                }
+
+                if let Some(position) = self.pending_accepts.iter().position(|&id| id == link_id) {
+                    self.pending_accepts.remove(position);
+                }
+            }
+            Some(URCMessages::DataAvailable {
+                link_id,
+                data,
+                remote,
+            }) => {
+                if let Some(link) = self.links.get_mut(link_id) {
+                    // Routed by this link's own tracked protocol, not by whether `remote`
+                    // is present: `AT+CIPDINFO` is a single global toggle, so once it's
+                    // enabled for UDP's sake a plain TCP/TLS `+IPD` carries the same
+                    // address fields and must not be misrouted into `rx_datagrams`.
+                    match (link.protocol, remote) {
+                        // UDP: buffered as its own unit so distinct senders are never
+                        // merged together; silently dropped if the per-link queue is
+                        // already full.
+                        (Some(LinkProtocol::Datagram), Some(remote)) => {
+                            let _ = link.rx_datagrams.push(Datagram { remote, data });
+                        }
+                        // UDP link without a sender address: `AT+CIPDINFO=1` is always sent
+                        // before any UDP link can be opened, so this should not happen.
+                        // Dropped rather than buffered under the wrong/no address.
+                        (Some(LinkProtocol::Datagram), None) => {}
+                        // TCP/TLS (or not yet connected): a chunk of a byte stream, so
+                        // concatenating is correct. Silently drops bytes that no longer fit;
+                        // callers are expected to drain the buffer via `receive()` often
+                        // enough to avoid this.
+                        (Some(LinkProtocol::Stream) | None, _) => {
+                            let _ = link.rx_buffer.extend_from_slice(&data);
+                        }
+                    }
+                }
+            }
+            Some(URCMessages::StationConnected) => {
+                self.connected_stations = self.connected_stations.saturating_add(1);
+            }
+            Some(URCMessages::StationDisconnected) => {
+                self.connected_stations = self.connected_stations.saturating_sub(1);
+            }
             Some(URCMessages::Unknown) => {}
             None => return false,
         };
@@ -128,4 +539,559 @@ impl<A: AtatClient> Adapter<A> {
             },
         }
     }
+
+    /// Configures the ESP32 as a WIFI access point, so other devices can join it directly.
+    /// The existing station connection, if any, is kept alive alongside it.
+    pub fn start_ap(&mut self, config: &ApConfig) -> Result<(), ApError> {
+        if config.ssid.len() > 32 {
+            return Err(ApError::InvalidSSIDLength);
+        }
+
+        if config.password.len() > 63 {
+            return Err(ApError::InvalidPasswordLength);
+        }
+
+        let mode = WifiModeCommand::station_and_ap_mode();
+        match self.client.send(&mode) {
+            Ok(_) => {}
+            Err(nb::Error::Other(other)) => return Err(ApError::ModeError(other)),
+            Err(nb::Error::WouldBlock) => return Err(ApError::UnexpectedWouldBlock),
+        }
+
+        let cwsap = CwsapCommand::new(
+            config.ssid,
+            config.password,
+            config.channel,
+            config.max_connections,
+        );
+        match self.client.send(&cwsap) {
+            Ok(_) => {}
+            Err(nb::Error::Other(other)) => return Err(ApError::ConfigureError(other)),
+            Err(nb::Error::WouldBlock) => return Err(ApError::UnexpectedWouldBlock),
+        }
+
+        if let Some((start_ip, end_ip, lease_time)) = config.dhcp_range {
+            let mut start = heapless::String::<16>::new();
+            let mut end = heapless::String::<16>::new();
+            use core::fmt::Write;
+            let _ = write!(start, "{}", start_ip);
+            let _ = write!(end, "{}", end_ip);
+
+            let dhcp = CwdhcpsCommand::new(lease_time, &start, &end);
+            match self.client.send(&dhcp) {
+                Ok(_) => {}
+                Err(nb::Error::Other(other)) => return Err(ApError::DhcpError(other)),
+                Err(nb::Error::WouldBlock) => return Err(ApError::UnexpectedWouldBlock),
+            }
+        }
+
+        self.process_urc_messages();
+        Ok(())
+    }
+
+    /// Tears down the SoftAP and reverts to pure station mode
+    pub fn close_ap(&mut self) -> Result<(), ApError> {
+        let mode = WifiModeCommand::station_mode();
+        match self.client.send(&mode) {
+            Ok(_) => {
+                self.connected_stations = 0;
+                Ok(())
+            }
+            Err(nb::Error::Other(other)) => Err(ApError::ModeError(other)),
+            Err(nb::Error::WouldBlock) => Err(ApError::UnexpectedWouldBlock),
+        }
+    }
+
+    /// Number of stations currently associated to our SoftAP
+    pub fn connected_stations(&self) -> u8 {
+        self.connected_stations
+    }
+
+    /// Sends `AT+CIPMUX=1` once, so up to `MAX_LINKS` sockets can be tracked at a time
+    fn ensure_multiplexing_enabled(&mut self) -> Result<(), SocketError> {
+        if self.multiplexing_enabled {
+            return Ok(());
+        }
+
+        let command = CipMuxCommand::multiple_connections();
+        match self.client.send(&command) {
+            Ok(_) => {}
+            Err(nb::Error::Other(other)) => return Err(SocketError::ModeError(other)),
+            Err(nb::Error::WouldBlock) => return Err(SocketError::UnexpectedWouldBlock),
+        }
+
+        // Needed so `UdpClientStack::receive()` can recover the sender's address.
+        // TCP/TLS links are unaffected by the extra fields this also puts on their own
+        // `+IPD` lines, since `handle_single_urc()` routes by each link's tracked
+        // `LinkProtocol` rather than by whether the fields are present.
+        let dinfo = CipDinfoCommand::enabled();
+        match self.client.send(&dinfo) {
+            Ok(_) => {}
+            Err(nb::Error::Other(other)) => return Err(SocketError::ModeError(other)),
+            Err(nb::Error::WouldBlock) => return Err(SocketError::UnexpectedWouldBlock),
+        }
+
+        self.multiplexing_enabled = true;
+        Ok(())
+    }
+
+    /// Picks a free link id, marks it as in use and returns it along with a fresh
+    /// [`Link::token`] for the `Socket`/`UdpSocket` being handed out
+    fn claim_link(&mut self) -> Result<(usize, u32), SocketError> {
+        let link_id = self
+            .links
+            .iter()
+            .position(|link| !link.in_use)
+            .ok_or(SocketError::NoAvailableLink)?;
+
+        let token = self.next_token();
+        self.links[link_id] = Link {
+            in_use: true,
+            token,
+            ..Link::default()
+        };
+
+        Ok((link_id, token))
+    }
+
+    /// Marks `link_id` as freshly connected once its `AT+CIPSTART` actually completes.
+    /// Clears any state left over from a previous connection on the same link id - the
+    /// caller is allowed to `connect()`/`connect_tls()` again on a `Socket` whose link
+    /// was closed by the peer instead of calling `close()` first, and without this the
+    /// stale `closed` flag and buffered bytes would be attributed to the new connection.
+    fn mark_connected(&mut self, link_id: usize, protocol: LinkProtocol) {
+        let link = &mut self.links[link_id];
+        link.connected = true;
+        link.closed = false;
+        link.protocol = Some(protocol);
+        link.rx_buffer.clear();
+        link.rx_datagrams.clear();
+    }
+
+    /// Opens a TLS connection on `socket`, reusing the plaintext send/receive path once
+    /// the handshake completes. `host` is used both to dial and, unless `config.sni`
+    /// overrides it, as the SNI hostname.
+    pub fn connect_tls(
+        &mut self,
+        socket: &mut Socket,
+        host: &str,
+        port: u16,
+        config: &TlsConfig,
+    ) -> nb::Result<(), SocketError> {
+        self.process_urc_messages();
+
+        // `AT+CIPSTART` is already in flight or resolved for this link; re-sending it
+        // (and re-running the SSL config commands) would be rejected by the module
+        // with `ALREADY CONNECT`/`ERROR`.
+        if self.links[socket.link_id].connected {
+            return Ok(());
+        }
+
+        if config.verify_mode != TlsVerifyMode::None {
+            let command = CipSslConfCommand::new(
+                socket.link_id,
+                config.verify_mode.auth_mode(),
+                config.client_cert_index.unwrap_or(0),
+                config.ca_cert_index.unwrap_or(0),
+            );
+            self.client.send(&command).map_err(|error| match error {
+                nb::Error::Other(other) => nb::Error::Other(SocketError::TlsHandshakeError(other)),
+                nb::Error::WouldBlock => nb::Error::Other(SocketError::UnexpectedWouldBlock),
+            })?;
+        }
+
+        let sni = CipSslSniCommand::new(socket.link_id, config.sni.unwrap_or(host));
+        self.client.send(&sni).map_err(|error| match error {
+            nb::Error::Other(other) => nb::Error::Other(SocketError::TlsHandshakeError(other)),
+            nb::Error::WouldBlock => nb::Error::Other(SocketError::UnexpectedWouldBlock),
+        })?;
+
+        let command = CipStartCommand::tls(socket.link_id, host, port);
+        self.client.send(&command).map_err(|error| match error {
+            nb::Error::Other(other) => nb::Error::Other(SocketError::TlsHandshakeError(other)),
+            nb::Error::WouldBlock => nb::Error::Other(SocketError::UnexpectedWouldBlock),
+        })?;
+
+        // `AT+CIPSTART` blocks until the handshake resolves, so a successful response
+        // means the link is up even if the separate `<id>,CONNECT` URC was already
+        // consumed as part of this command's own response and never observed here.
+        self.mark_connected(socket.link_id, LinkProtocol::Stream);
+        self.process_urc_messages();
+
+        Ok(())
+    }
+}
+
+impl<A: AtatClient> TcpClientStack for Adapter<A> {
+    type TcpSocket = Socket;
+    type Error = SocketError;
+
+    fn socket(&mut self) -> Result<Self::TcpSocket, Self::Error> {
+        self.ensure_multiplexing_enabled()?;
+        let (link_id, token) = self.claim_link()?;
+
+        Ok(Socket { link_id, token })
+    }
+
+    fn connect(
+        &mut self,
+        socket: &mut Self::TcpSocket,
+        remote: SocketAddr,
+    ) -> nb::Result<(), Self::Error> {
+        self.process_urc_messages();
+
+        // `AT+CIPSTART` is already in flight or resolved for this link; re-sending it
+        // would be rejected by the module with `ALREADY CONNECT`/`ERROR`.
+        if self.links[socket.link_id].connected {
+            return Ok(());
+        }
+
+        let host = format_ip(remote.ip()).map_err(|_| nb::Error::Other(SocketError::ConnectError(AtError::Parse)))?;
+
+        let command = CipStartCommand::tcp(socket.link_id, &host, remote.port());
+        self.client.send(&command).map_err(|error| match error {
+            nb::Error::Other(other) => nb::Error::Other(SocketError::ConnectError(other)),
+            nb::Error::WouldBlock => nb::Error::Other(SocketError::UnexpectedWouldBlock),
+        })?;
+
+        // `AT+CIPSTART` blocks until the connection resolves, so a successful response
+        // means the link is up even if the separate `<id>,CONNECT` URC was already
+        // consumed as part of this command's own response and never observed here.
+        self.mark_connected(socket.link_id, LinkProtocol::Stream);
+        self.process_urc_messages();
+
+        Ok(())
+    }
+
+    fn is_connected(&mut self, socket: &Self::TcpSocket) -> Result<bool, Self::Error> {
+        self.process_urc_messages();
+        Ok(self.links[socket.link_id].connected)
+    }
+
+    fn send(&mut self, socket: &mut Self::TcpSocket, buffer: &[u8]) -> nb::Result<usize, Self::Error> {
+        self.process_urc_messages();
+
+        if self.links[socket.link_id].closed {
+            return Err(nb::Error::Other(SocketError::ConnectionClosed));
+        }
+
+        let sent = buffer.len().min(MAX_SEND_LEN);
+        let header = CipSendCommand::new(socket.link_id, sent);
+        self.client.send(&header).map_err(|error| match error {
+            nb::Error::Other(other) => nb::Error::Other(SocketError::SendError(other)),
+            nb::Error::WouldBlock => nb::Error::Other(SocketError::UnexpectedWouldBlock),
+        })?;
+
+        self.client
+            .send(&SendDataCommand::new(&buffer[..sent]))
+            .map_err(|error| match error {
+                nb::Error::Other(other) => nb::Error::Other(SocketError::SendError(other)),
+                nb::Error::WouldBlock => nb::Error::Other(SocketError::UnexpectedWouldBlock),
+            })?;
+
+        Ok(sent)
+    }
+
+    fn receive(
+        &mut self,
+        socket: &mut Self::TcpSocket,
+        buffer: &mut [u8],
+    ) -> nb::Result<usize, Self::Error> {
+        self.process_urc_messages();
+
+        let link = &mut self.links[socket.link_id];
+        let len = core::cmp::min(buffer.len(), link.rx_buffer.len());
+
+        if len == 0 {
+            return if link.closed {
+                Ok(0)
+            } else {
+                Err(nb::Error::WouldBlock)
+            };
+        }
+
+        buffer[..len].copy_from_slice(&link.rx_buffer[..len]);
+        let remaining: Vec<u8, RX_BUFFER_SIZE> = link.rx_buffer[len..].iter().copied().collect();
+        link.rx_buffer = remaining;
+
+        Ok(len)
+    }
+
+    fn close(&mut self, socket: Self::TcpSocket) -> Result<(), Self::Error> {
+        // This link id may already have been reclaimed - e.g. `bind()` frees its
+        // listener socket's link id immediately - and reused for an unrelated
+        // connection; closing that connection instead of rejecting would be silent
+        // data loss for whoever now owns it.
+        if self.links[socket.link_id].token != socket.token {
+            return Err(SocketError::UnknownSocket);
+        }
+
+        let command = CipCloseCommand::new(socket.link_id);
+        match self.client.send(&command) {
+            Ok(_) => {}
+            Err(nb::Error::Other(other)) => return Err(SocketError::CloseError(other)),
+            Err(nb::Error::WouldBlock) => return Err(SocketError::UnexpectedWouldBlock),
+        }
+
+        self.links[socket.link_id] = Link::default();
+        Ok(())
+    }
+}
+
+impl<A: AtatClient> UdpClientStack for Adapter<A> {
+    type UdpSocket = UdpSocket;
+    type Error = SocketError;
+
+    fn socket(&mut self) -> Result<Self::UdpSocket, Self::Error> {
+        self.ensure_multiplexing_enabled()?;
+        let (link_id, token) = self.claim_link()?;
+
+        Ok(UdpSocket {
+            link_id,
+            token,
+            remote: None,
+        })
+    }
+
+    fn connect(&mut self, socket: &mut Self::UdpSocket, remote: SocketAddr) -> Result<(), Self::Error> {
+        let host = format_ip(remote.ip()).map_err(|_| SocketError::ConnectError(AtError::Parse))?;
+
+        // Arbitrary local port; ESP-AT picks one of its own when given 0.
+        let command = CipStartUdpCommand::new(socket.link_id, &host, remote.port(), 0);
+        self.client.send(&command).map_err(|error| match error {
+            nb::Error::Other(other) => SocketError::ConnectError(other),
+            nb::Error::WouldBlock => SocketError::UnexpectedWouldBlock,
+        })?;
+
+        self.links[socket.link_id].protocol = Some(LinkProtocol::Datagram);
+        socket.remote = Some(remote);
+        Ok(())
+    }
+
+    fn send(&mut self, socket: &mut Self::UdpSocket, buffer: &[u8]) -> nb::Result<(), Self::Error> {
+        let remote = socket.remote.ok_or(nb::Error::Other(SocketError::ConnectionClosed))?;
+        self.send_to(socket.link_id, remote, buffer)
+    }
+
+    fn receive(
+        &mut self,
+        socket: &mut Self::UdpSocket,
+        buffer: &mut [u8],
+    ) -> nb::Result<(usize, SocketAddr), Self::Error> {
+        self.process_urc_messages();
+
+        let link = &mut self.links[socket.link_id];
+        if link.rx_datagrams.is_empty() {
+            return Err(nb::Error::WouldBlock);
+        }
+
+        // Datagram boundaries are preserved: each `receive()` call drains exactly one
+        // buffered datagram, truncating it (rather than merging it with the next one)
+        // if `buffer` is too small to hold it whole.
+        let datagram = link.rx_datagrams.remove(0);
+        let len = core::cmp::min(buffer.len(), datagram.data.len());
+        buffer[..len].copy_from_slice(&datagram.data[..len]);
+
+        Ok((len, datagram.remote))
+    }
+
+    fn close(&mut self, socket: Self::UdpSocket) -> Result<(), Self::Error> {
+        // See `TcpClientStack::close()`: this link id may have been reclaimed and
+        // reused by an unrelated connection since this socket was issued.
+        if self.links[socket.link_id].token != socket.token {
+            return Err(SocketError::UnknownSocket);
+        }
+
+        let command = CipCloseCommand::new(socket.link_id);
+        match self.client.send(&command) {
+            Ok(_) => {}
+            Err(nb::Error::Other(other)) => return Err(SocketError::CloseError(other)),
+            Err(nb::Error::WouldBlock) => return Err(SocketError::UnexpectedWouldBlock),
+        }
+
+        self.links[socket.link_id] = Link::default();
+        Ok(())
+    }
+}
+
+impl<A: AtatClient> Adapter<A> {
+    /// Sends `buffer` as a single datagram on `link_id`, addressed to `remote`. The
+    /// only caller is `UdpClientStack::send`, which always passes the peer the socket
+    /// was given in `connect()`.
+    fn send_to(&mut self, link_id: usize, remote: SocketAddr, buffer: &[u8]) -> nb::Result<(), SocketError> {
+        // Unlike `TcpClientStack::send`, `UdpClientStack::send` returns no byte count
+        // to signal a short write with, so silently truncating an oversized datagram
+        // would deliver a corrupted payload to the peer without any error anywhere.
+        if buffer.len() > MAX_SEND_LEN {
+            return Err(nb::Error::Other(SocketError::DatagramTooLarge));
+        }
+
+        let host = format_ip(remote.ip()).map_err(|_| nb::Error::Other(SocketError::SendError(AtError::Parse)))?;
+
+        let header = CipSendToCommand::new(link_id, buffer.len(), &host, remote.port());
+        self.client.send(&header).map_err(|error| match error {
+            nb::Error::Other(other) => nb::Error::Other(SocketError::SendError(other)),
+            nb::Error::WouldBlock => nb::Error::Other(SocketError::UnexpectedWouldBlock),
+        })?;
+
+        self.client
+            .send(&SendDataCommand::new(buffer))
+            .map_err(|error| match error {
+                nb::Error::Other(other) => nb::Error::Other(SocketError::SendError(other)),
+                nb::Error::WouldBlock => nb::Error::Other(SocketError::UnexpectedWouldBlock),
+            })?;
+
+        Ok(())
+    }
+}
+
+impl<A: AtatClient> Dns for Adapter<A> {
+    type Error = DnsError;
+
+    fn get_host_by_name(&mut self, hostname: &str, addr_type: AddrType) -> nb::Result<IpAddr, Self::Error> {
+        let command = match addr_type {
+            AddrType::IPv4 => CipDomainCommand::new(hostname),
+            AddrType::IPv6 => CipDomainCommand::new_ipv6(hostname),
+            // `AT+CIPDOMAIN` resolves to a single address family per query; there is no
+            // "either" request to send the module, so reject rather than silently
+            // resolving IPv4 only.
+            AddrType::Either => return Err(nb::Error::Other(DnsError::Unsupported)),
+        };
+        let response = self.client.send(&command).map_err(|error| match error {
+            nb::Error::Other(other) => nb::Error::Other(DnsError::ResolveError(other)),
+            nb::Error::WouldBlock => nb::Error::Other(DnsError::UnexpectedWouldBlock),
+        })?;
+
+        response
+            .ip
+            .parse()
+            .map_err(|_| nb::Error::Other(DnsError::ResolveError(AtError::Parse)))
+    }
+
+    fn get_host_by_address(&mut self, _addr: IpAddr) -> nb::Result<heapless::String<256>, Self::Error> {
+        Err(nb::Error::Other(DnsError::Unsupported))
+    }
+}
+
+impl<A: AtatClient> TcpFullStack for Adapter<A> {
+    fn bind(&mut self, socket: &mut Self::TcpSocket, port: u16) -> Result<(), Self::Error> {
+        self.ensure_multiplexing_enabled()?;
+
+        let command = CipServerCommand::start(port);
+        self.client.send(&command).map_err(|error| match error {
+            nb::Error::Other(other) => SocketError::ConnectError(other),
+            nb::Error::WouldBlock => SocketError::UnexpectedWouldBlock,
+        })?;
+
+        self.server_port = Some(port);
+        // `AT+CIPSERVER` does not occupy one of the `MAX_LINKS` multiplexed link ids;
+        // `socket` merely carried the caller's intent to bind/listen/accept on this stack
+        // and inbound connections get their own link id handed out in `accept()`, so free
+        // it immediately rather than permanently burning a connection slot on it. Tear the
+        // server down with `stop_server()`, not `TcpFullStack::close()` on this socket -
+        // its link id no longer identifies an open connection and may since have been
+        // handed back out by `socket()`/`accept()`. `Link::default()` resets `token` to
+        // `0`, so `TcpClientStack::close()` rejects this `socket` (its `token` field is
+        // unchanged) instead of silently closing whatever now occupies the id.
+        self.links[socket.link_id] = Link::default();
+
+        Ok(())
+    }
+
+    fn listen(&mut self, _socket: &mut Self::TcpSocket) -> Result<(), Self::Error> {
+        // `AT+CIPSERVER` already put the module into listening state during `bind()`
+        Ok(())
+    }
+
+    fn accept(&mut self, _socket: &mut Self::TcpSocket) -> nb::Result<(Self::TcpSocket, SocketAddr), Self::Error> {
+        self.process_urc_messages();
+
+        let link_id = if self.pending_accepts.is_empty() {
+            return Err(nb::Error::WouldBlock);
+        } else {
+            self.pending_accepts.remove(0)
+        };
+
+        // The `<id>,CONNECT` URC does not carry the peer address; ask the module for it
+        // explicitly via `AT+CIPSTATE?`, which reports the remote ip/port of every open link.
+        let command = CipStateQueryCommand::new();
+        let lines = self.client.send(&command).map_err(|error| match error {
+            nb::Error::Other(other) => nb::Error::Other(SocketError::ConnectError(other)),
+            nb::Error::WouldBlock => nb::Error::Other(SocketError::UnexpectedWouldBlock),
+        })?;
+
+        let remote = lines
+            .iter()
+            .find(|line| line.link_id == link_id)
+            .and_then(|line| line.remote_ip.parse::<IpAddr>().ok().map(|ip| SocketAddr::new(ip, line.remote_port)));
+
+        match remote {
+            // `handle_single_urc()` minted a fresh `Link::token` for this id when it
+            // accepted the connection; captured here so this `Socket` can be told
+            // apart from a later one that reuses the same id.
+            Some(remote) => Ok((
+                Socket {
+                    link_id,
+                    token: self.links[link_id].token,
+                },
+                remote,
+            )),
+            // The link may not be listed yet if `AT+CIPSTATE?` raced the `CONNECT` URC; put
+            // it back at the front of the queue and retry on the next `accept()` call
+            // rather than handing back a bogus peer address.
+            None => {
+                let _ = self.pending_accepts.insert(0, link_id);
+                Err(nb::Error::WouldBlock)
+            }
+        }
+    }
+}
+
+impl<A: AtatClient> Adapter<A> {
+    /// Stops the TCP server started by `bind()`, rejecting any further inbound connections
+    pub fn stop_server(&mut self) -> Result<(), SocketError> {
+        let command = CipServerCommand::stop();
+        match self.client.send(&command) {
+            Ok(_) => {
+                self.server_port = None;
+                Ok(())
+            }
+            Err(nb::Error::Other(other)) => Err(SocketError::CloseError(other)),
+            Err(nb::Error::WouldBlock) => Err(SocketError::UnexpectedWouldBlock),
+        }
+    }
+}
+
+/// Formats an IP address into the quoted-string form ESP-AT commands expect
+fn format_ip(ip: embedded_nal::IpAddr) -> Result<heapless::String<64>, core::fmt::Error> {
+    use core::fmt::Write;
+    let mut buffer = heapless::String::<64>::new();
+    write!(buffer, "{}", ip)?;
+    Ok(buffer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cwstate_maps_known_codes() {
+        assert_eq!(cwstate_to_connection_status(0), ConnectionStatus::NotStarted);
+        assert_eq!(cwstate_to_connection_status(1), ConnectionStatus::Connected);
+        assert_eq!(cwstate_to_connection_status(2), ConnectionStatus::GotIp);
+        assert_eq!(cwstate_to_connection_status(3), ConnectionStatus::Connecting);
+        assert_eq!(cwstate_to_connection_status(4), ConnectionStatus::Disconnected);
+    }
+
+    #[test]
+    fn cwstate_maps_unrecognized_code_to_connect_failed() {
+        assert_eq!(cwstate_to_connection_status(255), ConnectionStatus::ConnectFailed);
+    }
+
+    #[test]
+    fn tls_verify_mode_maps_to_cipsslcconf_auth_mode() {
+        assert_eq!(TlsVerifyMode::None.auth_mode(), 0);
+        assert_eq!(TlsVerifyMode::ServerOnly.auth_mode(), 2);
+        assert_eq!(TlsVerifyMode::Mutual.auth_mode(), 3);
+    }
 }
@@ -0,0 +1,484 @@
+use atat::atat_derive::{AtatCmd, AtatResp};
+use atat::{AtatCmd, Error as AtError, InternalError};
+use heapless::{String, Vec};
+
+/// Number of multiplexed links ESP-AT tracks once `AT+CIPMUX=1` is set
+pub(crate) const MAX_LINKS: usize = 5;
+
+/// Response for commands that only return `OK`/`ERROR`
+#[derive(Clone, AtatResp)]
+pub struct NoResponse;
+
+/// WIFI mode as used by `AT+CWMODE`. Lists the full value range of the AT
+/// command even though `Adapter` only ever constructs a subset of them.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[allow(dead_code)]
+pub enum WifiMode {
+    /// WIFI radio is switched off
+    None = 0,
+
+    /// Client/station mode
+    Station = 1,
+
+    /// Access point mode
+    SoftAP = 2,
+
+    /// Station and access point mode at the same time
+    StationAndSoftAP = 3,
+}
+
+/// `AT+CWMODE` - Sets the current WIFI mode
+#[derive(Clone, AtatCmd)]
+#[at_cmd("+CWMODE", NoResponse, timeout_ms = 1000)]
+pub struct WifiModeCommand {
+    mode: u8,
+}
+
+impl WifiModeCommand {
+    /// Switches the radio into station (client) mode
+    pub fn station_mode() -> Self {
+        Self {
+            mode: WifiMode::Station as u8,
+        }
+    }
+
+    /// Switches the radio into station + SoftAP mode, so an access point can
+    /// be hosted without losing the existing station connection
+    pub fn station_and_ap_mode() -> Self {
+        Self {
+            mode: WifiMode::StationAndSoftAP as u8,
+        }
+    }
+}
+
+/// `AT+CWJAP` - Connects to an access point using the given credentials
+#[derive(Clone, AtatCmd)]
+#[at_cmd("+CWJAP", NoResponse, timeout_ms = 20000)]
+pub struct AccessPointConnectCommand {
+    ssid: String<32>,
+    key: String<64>,
+}
+
+impl AccessPointConnectCommand {
+    /// Creates a new command for the given SSID/password pair
+    pub fn new(ssid: String<32>, key: String<64>) -> Self {
+        Self { ssid, key }
+    }
+}
+
+/// `AT+CIPMUX` - Switches between single-connection and multi-connection mode.
+/// Multi-connection mode is required to track more than one socket at a time.
+#[derive(Clone, AtatCmd)]
+#[at_cmd("+CIPMUX", NoResponse, timeout_ms = 1000)]
+pub struct CipMuxCommand {
+    enabled: u8,
+}
+
+impl CipMuxCommand {
+    /// Enables multi-connection mode, making up to 5 link ids available
+    pub fn multiple_connections() -> Self {
+        Self { enabled: 1 }
+    }
+}
+
+/// `AT+CIPDINFO` - Configures whether `+IPD` notifications carry the sender's
+/// `<ip>,<port>`. This is a single global toggle covering every link regardless of
+/// protocol, not a per-connection option; `Adapter` tells TCP/TLS and UDP `+IPD`
+/// apart by tracking each link's own protocol rather than relying on this.
+#[derive(Clone, AtatCmd)]
+#[at_cmd("+CIPDINFO", NoResponse, timeout_ms = 1000)]
+pub struct CipDinfoCommand {
+    enabled: u8,
+}
+
+impl CipDinfoCommand {
+    /// Turns the `<ip>,<port>` fields on
+    pub fn enabled() -> Self {
+        Self { enabled: 1 }
+    }
+}
+
+/// `AT+CIPSTART` - Opens a TCP or TLS connection on the given link id
+#[derive(Clone, AtatCmd)]
+#[at_cmd("+CIPSTART", NoResponse, timeout_ms = 10000)]
+pub struct CipStartCommand {
+    link_id: usize,
+    mode: String<4>,
+    host: String<64>,
+    port: u16,
+}
+
+impl CipStartCommand {
+    /// Builds the command to open a plain TCP connection
+    pub fn tcp(link_id: usize, host: &str, port: u16) -> Self {
+        Self {
+            link_id,
+            mode: String::from("TCP"),
+            host: String::from(host),
+            port,
+        }
+    }
+
+    /// Builds the command to open a TLS ("SSL") connection
+    pub fn tls(link_id: usize, host: &str, port: u16) -> Self {
+        Self {
+            link_id,
+            mode: String::from("SSL"),
+            host: String::from(host),
+            port,
+        }
+    }
+}
+
+/// `AT+CIPSTART` - Opens a UDP "connection" on the given link id. Unlike TCP
+/// this merely reserves a local/remote port pair; datagrams can still be sent
+/// to a different remote via [`CipSendToCommand`].
+#[derive(Clone, AtatCmd)]
+#[at_cmd("+CIPSTART", NoResponse, timeout_ms = 10000)]
+pub struct CipStartUdpCommand {
+    link_id: usize,
+    mode: String<4>,
+    host: String<64>,
+    port: u16,
+    local_port: u16,
+    udp_mode: u8,
+}
+
+impl CipStartUdpCommand {
+    /// Builds the command to open a UDP socket bound to `local_port`, with
+    /// `host`/`port` used as the default remote peer
+    pub fn new(link_id: usize, host: &str, port: u16, local_port: u16) -> Self {
+        Self {
+            link_id,
+            mode: String::from("UDP"),
+            host: String::from(host),
+            port,
+            local_port,
+            // 0: remote peer is fixed at "connect" time and not changed by received datagrams
+            udp_mode: 0,
+        }
+    }
+}
+
+/// `AT+CIPSEND` - Announces an upcoming payload of `len` bytes on `link_id`.
+/// The module answers with a `>` prompt once it is ready to receive the raw
+/// bytes, which are then written out-of-band using [`SendDataCommand`].
+#[derive(Clone, AtatCmd)]
+#[at_cmd("+CIPSEND", NoResponse, timeout_ms = 5000)]
+pub struct CipSendCommand {
+    link_id: usize,
+    len: usize,
+}
+
+impl CipSendCommand {
+    /// Creates the send announcement for `len` bytes on `link_id`
+    pub fn new(link_id: usize, len: usize) -> Self {
+        Self { link_id, len }
+    }
+}
+
+/// `AT+CIPSEND` - Announces an upcoming datagram of `len` bytes on `link_id`,
+/// addressed to `<host>:<port>` regardless of the peer given at connect time
+#[derive(Clone, AtatCmd)]
+#[at_cmd("+CIPSEND", NoResponse, timeout_ms = 5000)]
+pub struct CipSendToCommand {
+    link_id: usize,
+    len: usize,
+    host: String<64>,
+    port: u16,
+}
+
+impl CipSendToCommand {
+    /// Creates the send announcement for `len` bytes on `link_id`, addressed
+    /// to `host`/`port`
+    pub fn new(link_id: usize, len: usize, host: &str, port: u16) -> Self {
+        Self {
+            link_id,
+            len,
+            host: String::from(host),
+            port,
+        }
+    }
+}
+
+/// Maximum payload written in a single [`SendDataCommand`]
+pub const MAX_SEND_LEN: usize = 512;
+
+/// Raw payload written after a `AT+CIPSEND` prompt. Unlike the other commands
+/// this carries no `AT+...` prefix: the module is already waiting for exactly
+/// as many bytes as announced and answers with `SEND OK`.
+#[derive(Clone)]
+pub struct SendDataCommand {
+    data: Vec<u8, MAX_SEND_LEN>,
+}
+
+impl SendDataCommand {
+    /// Creates a raw payload command for the given bytes, silently capped to
+    /// `MAX_SEND_LEN`. Callers must announce the same capped length via
+    /// `AT+CIPSEND` ([`CipSendCommand`]/[`CipSendToCommand`]) so the module does
+    /// not wait for bytes that are never written.
+    pub fn new(data: &[u8]) -> Self {
+        let mut buffer = Vec::new();
+        let _ = buffer.extend_from_slice(&data[..data.len().min(MAX_SEND_LEN)]);
+        Self { data: buffer }
+    }
+}
+
+impl AtatCmd<MAX_SEND_LEN> for SendDataCommand {
+    type Response = NoResponse;
+
+    fn as_bytes(&self) -> Vec<u8, MAX_SEND_LEN> {
+        self.data.clone()
+    }
+
+    fn parse(&self, _resp: Result<&[u8], InternalError>) -> Result<Self::Response, AtError> {
+        Ok(NoResponse)
+    }
+}
+
+/// Encryption mode as used by `AT+CWSAP`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ApEncryption {
+    /// No encryption, open network
+    Open = 0,
+
+    /// WPA2-PSK
+    Wpa2Psk = 3,
+}
+
+/// `AT+CWSAP` - Configures the SoftAP SSID, password, channel and encryption
+#[derive(Clone, AtatCmd)]
+#[at_cmd("+CWSAP", NoResponse, timeout_ms = 5000)]
+pub struct CwsapCommand {
+    ssid: String<32>,
+    password: String<64>,
+    channel: u8,
+    ecn: u8,
+    max_connections: u8,
+}
+
+impl CwsapCommand {
+    /// Builds the SoftAP configuration command. `password` being empty opens the network.
+    pub fn new(ssid: &str, password: &str, channel: u8, max_connections: u8) -> Self {
+        let ecn = if password.is_empty() {
+            ApEncryption::Open
+        } else {
+            ApEncryption::Wpa2Psk
+        };
+
+        Self {
+            ssid: String::from(ssid),
+            password: String::from(password),
+            channel,
+            ecn: ecn as u8,
+            max_connections,
+        }
+    }
+}
+
+/// `AT+CWDHCPS` - Configures the SoftAP DHCP lease time and address range handed
+/// out to joining stations. ESP-AT has no DHCP option for advertising a DNS
+/// server, so a captive-portal redirect has to happen at the application layer.
+#[derive(Clone, AtatCmd)]
+#[at_cmd("+CWDHCPS", NoResponse, timeout_ms = 5000)]
+pub struct CwdhcpsCommand {
+    enable: u8,
+    lease_time: u16,
+    start_ip: String<16>,
+    end_ip: String<16>,
+}
+
+impl CwdhcpsCommand {
+    /// Builds the DHCP server configuration for the given lease time (in minutes)
+    /// and address range
+    pub fn new(lease_time: u16, start_ip: &str, end_ip: &str) -> Self {
+        Self {
+            enable: 1,
+            lease_time,
+            start_ip: String::from(start_ip),
+            end_ip: String::from(end_ip),
+        }
+    }
+}
+
+/// `AT+CWSTATE?` - Queries the current WIFI association state
+#[derive(Clone, AtatCmd)]
+#[at_cmd("+CWSTATE?", CwStateResponse, timeout_ms = 1000)]
+pub struct CwStateQueryCommand;
+
+impl CwStateQueryCommand {
+    /// Builds the query command
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+/// Response to `AT+CWSTATE?`: `+CWSTATE:<state>,<ssid>`
+#[derive(Clone, atat::atat_derive::AtatResp)]
+pub struct CwStateResponse {
+    pub state: u8,
+
+    /// SSID of the associated access point. Not currently surfaced by
+    /// `Adapter`, but kept here since the response line always carries it.
+    #[allow(dead_code)]
+    pub ssid: String<32>,
+}
+
+/// `AT+CIPDOMAIN` - Resolves a hostname to an IP address
+#[derive(Clone, AtatCmd)]
+#[at_cmd("+CIPDOMAIN", CipDomainResponse, timeout_ms = 10000)]
+pub struct CipDomainCommand {
+    hostname: String<253>,
+    ip_version: u8,
+}
+
+impl CipDomainCommand {
+    /// Resolves `hostname` to an IPv4 address
+    pub fn new(hostname: &str) -> Self {
+        Self {
+            hostname: String::from(hostname),
+            ip_version: 4,
+        }
+    }
+
+    /// Resolves `hostname` to an IPv6 address
+    pub fn new_ipv6(hostname: &str) -> Self {
+        Self {
+            hostname: String::from(hostname),
+            ip_version: 6,
+        }
+    }
+}
+
+/// Response to `AT+CIPDOMAIN`: `+CIPDOMAIN:<ip>`
+#[derive(Clone, atat::atat_derive::AtatResp)]
+pub struct CipDomainResponse {
+    pub ip: String<64>,
+}
+
+/// `AT+CIPSTATE?` - Queries the remote address of every currently open link.
+/// Unlike `AT+CWSTATE?` this reports the per-socket peer, not the WIFI
+/// association, and answers with one `+CIPSTATE:` line per open link.
+#[derive(Clone, AtatCmd)]
+#[at_cmd("+CIPSTATE?", Vec<CipStateLine, MAX_LINKS>, timeout_ms = 1000)]
+pub struct CipStateQueryCommand;
+
+impl CipStateQueryCommand {
+    /// Builds the query command
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+/// One `+CIPSTATE:<link_id>,<"type">,<remote_ip>,<remote_port>,<local_port>,<tetype>` line
+#[derive(Clone, atat::atat_derive::AtatResp)]
+pub struct CipStateLine {
+    pub link_id: usize,
+
+    /// `"TCP"`/`"UDP"`/`"SSL"`. Not currently surfaced by `Adapter`.
+    #[allow(dead_code)]
+    pub mode: String<4>,
+
+    pub remote_ip: String<64>,
+    pub remote_port: u16,
+
+    /// Not currently surfaced by `Adapter`.
+    #[allow(dead_code)]
+    pub local_port: u16,
+
+    /// `0` for a link we dialed, `1` for one accepted by our server. Not currently surfaced by `Adapter`.
+    #[allow(dead_code)]
+    pub tetype: u8,
+}
+
+/// `AT+CIPSERVER` - Starts or stops the TCP server. Requires `AT+CIPMUX=1`.
+#[derive(Clone, AtatCmd)]
+#[at_cmd("+CIPSERVER", NoResponse, timeout_ms = 5000)]
+pub struct CipServerCommand {
+    enabled: u8,
+    port: u16,
+}
+
+impl CipServerCommand {
+    /// Starts listening for inbound connections on `port`
+    pub fn start(port: u16) -> Self {
+        Self { enabled: 1, port }
+    }
+
+    /// Stops the server, dropping any further inbound connections
+    pub fn stop() -> Self {
+        Self { enabled: 0, port: 0 }
+    }
+}
+
+/// `AT+CIPCLOSE` - Closes the connection on the given link id
+#[derive(Clone, AtatCmd)]
+#[at_cmd("+CIPCLOSE", NoResponse, timeout_ms = 5000)]
+pub struct CipCloseCommand {
+    link_id: usize,
+}
+
+impl CipCloseCommand {
+    /// Creates the command to close the connection on `link_id`
+    pub fn new(link_id: usize) -> Self {
+        Self { link_id }
+    }
+}
+
+/// `AT+CIPSSLCCONF` - Configures the TLS context used by a subsequent `AT+CIPSTART=...,"SSL",...`
+/// on the given link id: authentication mode plus the flash slots of the CA/client certificates
+#[derive(Clone, AtatCmd)]
+#[at_cmd("+CIPSSLCCONF", NoResponse, timeout_ms = 1000)]
+pub struct CipSslConfCommand {
+    link_id: usize,
+    auth_mode: u8,
+    pki_number: u8,
+    ca_number: u8,
+}
+
+impl CipSslConfCommand {
+    /// Builds the TLS context configuration for `link_id`. Certificate slots are indices into
+    /// certificates preloaded into ESP-AT flash; `0` means "use the first/only preloaded one".
+    pub fn new(link_id: usize, auth_mode: u8, client_cert_index: u8, ca_cert_index: u8) -> Self {
+        Self {
+            link_id,
+            auth_mode,
+            pki_number: client_cert_index,
+            ca_number: ca_cert_index,
+        }
+    }
+}
+
+/// `AT+CIPSSLCSNI` - Sets the SNI hostname presented during the TLS handshake on `link_id`
+#[derive(Clone, AtatCmd)]
+#[at_cmd("+CIPSSLCSNI", NoResponse, timeout_ms = 1000)]
+pub struct CipSslSniCommand {
+    link_id: usize,
+    sni: String<253>,
+}
+
+impl CipSslSniCommand {
+    /// Builds the SNI configuration command
+    pub fn new(link_id: usize, sni: &str) -> Self {
+        Self {
+            link_id,
+            sni: String::from(sni),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cwdhcps_serializes_enable_lease_time_then_address_range() {
+        let cmd = CwdhcpsCommand::new(5, "192.168.4.10", "192.168.4.20");
+        let bytes = cmd.as_bytes();
+
+        assert_eq!(
+            core::str::from_utf8(&bytes).unwrap(),
+            "AT+CWDHCPS=1,5,\"192.168.4.10\",\"192.168.4.20\"\r\n"
+        );
+    }
+}
@@ -0,0 +1,10 @@
+#![no_std]
+
+//! Network Abstraction Layer (NAL) for ESP-AT capable devices, built on top of
+//! [`atat`] and exposing the [`embedded_nal`] traits.
+
+pub mod adapter;
+pub(crate) mod commands;
+pub(crate) mod urc;
+
+pub use adapter::Adapter;
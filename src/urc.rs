@@ -0,0 +1,250 @@
+use atat::AtatUrc;
+use embedded_nal::SocketAddr;
+use heapless::Vec;
+
+/// Maximum number of payload bytes kept from a single `+IPD` notification. A
+/// longer chunk is truncated to this size by `parse_ipd()`.
+pub const MAX_IPD_CHUNK: usize = 512;
+
+/// Unsolicited result codes (URCs) emitted by the ESP-AT firmware
+// `DataAvailable` is considerably larger than the other variants because it embeds
+// the `+IPD` payload inline; boxing it would require `alloc`, which this `no_std`
+// crate does not pull in, so the size difference is accepted here.
+#[derive(Clone, Debug, PartialEq)]
+#[allow(clippy::large_enum_variant)]
+pub enum URCMessages {
+    /// `WIFI CONNECTED` - the module associated with an access point
+    WifiConnected,
+
+    /// `WIFI DISCONNECT` - the module lost the association to its access point
+    WifiDisconnected,
+
+    /// `WIFI GOT IP` - an IP address was assigned by the access point
+    ReceivedIP,
+
+    /// `ready` - the module finished booting
+    Ready,
+
+    /// `<link_id>,CONNECT` - a multiplexed link finished connecting
+    LinkConnected(usize),
+
+    /// `<link_id>,CLOSED` - a multiplexed link was closed, either by the peer
+    /// or in response to `AT+CIPCLOSE`
+    LinkClosed(usize),
+
+    /// `+IPD,<link_id>,<len>[,<ip>,<port>]:<data>` - inbound payload on a multiplexed
+    /// link. The `<ip>,<port>` fields are controlled by `AT+CIPDINFO`, a single global
+    /// toggle covering every link regardless of protocol - so their presence here does
+    /// *not* by itself mean the payload is a UDP datagram; routing a `+IPD` correctly
+    /// requires knowing the link's own protocol (see `Adapter`'s `Link::protocol`).
+    DataAvailable {
+        /// Link the payload arrived on
+        link_id: usize,
+
+        /// Raw payload bytes
+        data: Vec<u8, MAX_IPD_CHUNK>,
+
+        /// Sender address, present on every link once `AT+CIPDINFO=1` is in effect
+        remote: Option<SocketAddr>,
+    },
+
+    /// `+STA_CONNECTED` - a station joined our SoftAP
+    StationConnected,
+
+    /// `+STA_DISCONNECTED` - a station left our SoftAP
+    StationDisconnected,
+
+    /// Any URC that could not be matched against a known pattern
+    Unknown,
+}
+
+impl AtatUrc for URCMessages {
+    type Response = Self;
+
+    fn parse(resp: &[u8]) -> Option<Self::Response> {
+        let trimmed = trim_ascii_whitespace(resp);
+
+        // Matched on raw bytes, before any UTF-8 conversion: the `+IPD` payload can be
+        // arbitrary binary data (e.g. a TLS record), which would make a whole-line
+        // `from_utf8` fail and silently drop the notification.
+        if let Some(rest) = trimmed.strip_prefix(b"+IPD,") {
+            return parse_ipd(rest);
+        }
+
+        let line = core::str::from_utf8(trimmed).ok()?;
+
+        if line == "WIFI CONNECTED" {
+            return Some(Self::WifiConnected);
+        }
+
+        if line == "WIFI DISCONNECT" {
+            return Some(Self::WifiDisconnected);
+        }
+
+        if line == "WIFI GOT IP" {
+            return Some(Self::ReceivedIP);
+        }
+
+        if line == "ready" {
+            return Some(Self::Ready);
+        }
+
+        if let Some(link_id) = line.strip_suffix(",CONNECT").and_then(parse_link_id) {
+            return Some(Self::LinkConnected(link_id));
+        }
+
+        if let Some(link_id) = line.strip_suffix(",CLOSED").and_then(parse_link_id) {
+            return Some(Self::LinkClosed(link_id));
+        }
+
+        if line.starts_with("+STA_CONNECTED") {
+            return Some(Self::StationConnected);
+        }
+
+        if line.starts_with("+STA_DISCONNECTED") {
+            return Some(Self::StationDisconnected);
+        }
+
+        Some(Self::Unknown)
+    }
+}
+
+/// Parses `prefix`, the part of a `<link_id>,<rest>` URC before the comma, as a link id
+fn parse_link_id(prefix: &str) -> Option<usize> {
+    prefix.parse().ok()
+}
+
+/// Strips leading/trailing ASCII whitespace (space, CR, LF, tab) without requiring
+/// the buffer to be valid UTF-8, since a `+IPD` line may carry a binary payload
+#[allow(clippy::manual_is_ascii_check)]
+fn trim_ascii_whitespace(mut bytes: &[u8]) -> &[u8] {
+    while let [first, rest @ ..] = bytes {
+        if first.is_ascii_whitespace() {
+            bytes = rest;
+        } else {
+            break;
+        }
+    }
+
+    while let [rest @ .., last] = bytes {
+        if last.is_ascii_whitespace() {
+            bytes = rest;
+        } else {
+            break;
+        }
+    }
+
+    bytes
+}
+
+/// Parses the body of a `+IPD,<link_id>,<len>[,<ip>,<port>]:<data>` URC, with
+/// `rest` being the raw bytes after the `+IPD,` prefix. The `<ip>,<port>` fields are
+/// present on every link once `AT+CIPDINFO=1` is sent, regardless of protocol - the
+/// caller, not this parser, decides what a present `remote` means for a given link.
+/// The header is always ASCII, but `<data>` is copied straight from `rest` so binary
+/// payloads are never passed through UTF-8 validation.
+fn parse_ipd(rest: &[u8]) -> Option<URCMessages> {
+    let colon = rest.iter().position(|&byte| byte == b':')?;
+    let header = core::str::from_utf8(&rest[..colon]).ok()?;
+    let data = &rest[colon + 1..];
+
+    let mut fields = header.split(',');
+
+    let link_id: usize = fields.next()?.parse().ok()?;
+    let len: usize = fields.next()?.parse().ok()?;
+
+    let remote = match (fields.next(), fields.next()) {
+        (Some(ip), Some(port)) => Some(SocketAddr::new(ip.parse().ok()?, port.parse().ok()?)),
+        _ => None,
+    };
+
+    if data.len() < len {
+        return None;
+    }
+
+    // A chunk above `MAX_IPD_CHUNK` is truncated rather than dropped, mirroring the
+    // send-side cap to `MAX_SEND_LEN`: `data` is stored in a `Vec<u8, MAX_IPD_CHUNK>`
+    // regardless, so the bytes beyond it could never have been delivered to the
+    // caller, but the notification itself (link id, remote address) must survive.
+    let truncated = len.min(MAX_IPD_CHUNK);
+    let mut buffer = Vec::new();
+    buffer.extend_from_slice(&data[..truncated]).ok()?;
+
+    Some(URCMessages::DataAvailable {
+        link_id,
+        data: buffer,
+        remote,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data_available(link_id: usize, data: &[u8], remote: Option<SocketAddr>) -> URCMessages {
+        let mut buffer = Vec::new();
+        buffer.extend_from_slice(data).unwrap();
+        URCMessages::DataAvailable {
+            link_id,
+            data: buffer,
+            remote,
+        }
+    }
+
+    #[test]
+    fn parses_plain_tcp_ipd() {
+        let parsed = URCMessages::parse(b"+IPD,0,5:hello").unwrap();
+        assert_eq!(parsed, data_available(0, b"hello", None));
+    }
+
+    #[test]
+    fn parses_udp_addressed_ipd() {
+        let parsed = URCMessages::parse(b"+IPD,2,3,192.168.1.10,1234:abc").unwrap();
+        let remote = SocketAddr::new(embedded_nal::Ipv4Addr::new(192, 168, 1, 10).into(), 1234);
+        assert_eq!(parsed, data_available(2, b"abc", Some(remote)));
+    }
+
+    #[test]
+    fn oversized_ipd_chunk_is_truncated_not_dropped() {
+        use core::fmt::Write;
+
+        let oversized = [b'x'; MAX_IPD_CHUNK + 16];
+        let mut header: heapless::String<16> = heapless::String::new();
+        write!(header, "+IPD,1,{}:", oversized.len()).unwrap();
+
+        let mut resp = Vec::<u8, { MAX_IPD_CHUNK + 64 }>::new();
+        resp.extend_from_slice(header.as_bytes()).unwrap();
+        resp.extend_from_slice(&oversized).unwrap();
+
+        let parsed = URCMessages::parse(&resp).unwrap();
+        match parsed {
+            URCMessages::DataAvailable { link_id, data, remote } => {
+                assert_eq!(link_id, 1);
+                assert_eq!(remote, None);
+                assert_eq!(data.len(), MAX_IPD_CHUNK);
+                assert!(data.iter().all(|&byte| byte == b'x'));
+            }
+            other => panic!("expected DataAvailable, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn parses_link_connect_and_closed() {
+        assert_eq!(URCMessages::parse(b"3,CONNECT").unwrap(), URCMessages::LinkConnected(3));
+        assert_eq!(URCMessages::parse(b"3,CLOSED").unwrap(), URCMessages::LinkClosed(3));
+    }
+
+    #[test]
+    fn parses_station_connected_and_disconnected() {
+        assert_eq!(URCMessages::parse(b"+STA_CONNECTED").unwrap(), URCMessages::StationConnected);
+        assert_eq!(
+            URCMessages::parse(b"+STA_DISCONNECTED").unwrap(),
+            URCMessages::StationDisconnected
+        );
+    }
+
+    #[test]
+    fn unmatched_line_is_unknown_not_discarded() {
+        assert_eq!(URCMessages::parse(b"SEND OK").unwrap(), URCMessages::Unknown);
+    }
+}